@@ -0,0 +1,61 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module stores the database configuration types.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Whether (and where) the database is persisted to disk
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PersistType {
+	/// Keep the database in memory only; nothing is written to disk
+	Memory,
+	/// Persist the database to the given file
+	File(PathBuf),
+}
+
+/// When a commit is flushed to the backing file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncPolicy {
+	/// Never sync on commit; the backing file is only rewritten by
+	/// `Db::compact`, which does so only when a commit log is configured
+	Never,
+	/// Sync to the backing file on every commit
+	Always,
+	/// Sync to the backing file at most once per the given interval
+	Every(Duration),
+}
+
+/// Configuration used when opening a database
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+	/// Whether (and where) the database is persisted to disk
+	pub persist: PersistType,
+	/// When a commit is flushed to the backing file
+	pub sync: SyncPolicy,
+	/// An optional append-only commit log, used alongside a `File` snapshot
+	/// so that individual commits don't require rewriting the whole map
+	pub log: Option<PathBuf>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			persist: PersistType::Memory,
+			sync: SyncPolicy::Never,
+			log: None,
+		}
+	}
+}
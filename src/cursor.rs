@@ -0,0 +1,201 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module stores the cursor logic for bidirectional range iteration.
+
+use imbl::OrdMap;
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+// The cursor's position relative to the entries in its range
+//
+// `New` is the cursor's starting state, before either `next` or `prev` has
+// been called: both directions are still open, so `next` scans from `lo`
+// and `prev` scans from `hi`. `Before`/`After` are fenceposts reached by
+// actually stepping off the low/high end (via `next`, `prev`, or a
+// fruitless `seek`); unlike `New` they're one-directional, so the cursor
+// stays exhausted in that direction instead of wrapping back around on
+// the next call. Keeping `New` distinct from `Before` is what lets `prev`
+// work as the very first call on a fresh cursor.
+enum Pos<K> {
+	New,
+	At(K),
+	Before,
+	After,
+}
+
+/// A bidirectional cursor over a range of keys in a transaction's data map
+///
+/// A `Cursor` borrows the transaction's immutable data map, so it always
+/// stays consistent with the transaction's snapshot and needs no locking.
+pub struct Cursor<'a, K, V> {
+	ds: &'a OrdMap<K, V>,
+	lo: Bound<K>,
+	hi: Bound<K>,
+	pos: Pos<K>,
+	// The owning transaction's snapshot and read set, used to record every
+	// key this cursor yields for OCC conflict detection, exactly like
+	// `Tx::get`/`exi`/`scan`/`scan_rev` already do. `None` for a standalone
+	// cursor with no owning transaction.
+	reads: Option<(&'a OrdMap<K, V>, &'a mut BTreeMap<K, Option<V>>)>,
+}
+
+impl<'a, K, V> Cursor<'a, K, V>
+where
+	K: Ord + Clone,
+	V: Clone,
+{
+	/// Create a new cursor over `ds`, restricted to `range`
+	pub(crate) fn new(ds: &'a OrdMap<K, V>, range: impl RangeBounds<K>) -> Cursor<'a, K, V> {
+		Cursor {
+			ds,
+			lo: range.start_bound().cloned(),
+			hi: range.end_bound().cloned(),
+			pos: Pos::New,
+			reads: None,
+		}
+	}
+	/// Create a new cursor over `ds`, restricted to `range`, that records
+	/// every key it yields into `reads` using the value observed in `snap`
+	/// at the start of the owning transaction
+	pub(crate) fn tracked(
+		ds: &'a OrdMap<K, V>,
+		range: impl RangeBounds<K>,
+		snap: &'a OrdMap<K, V>,
+		reads: &'a mut BTreeMap<K, Option<V>>,
+	) -> Cursor<'a, K, V> {
+		Cursor {
+			ds,
+			lo: range.start_bound().cloned(),
+			hi: range.end_bound().cloned(),
+			pos: Pos::New,
+			reads: Some((snap, reads)),
+		}
+	}
+	// Record `key` in the owning transaction's read set, if any, using the
+	// value observed at the start of the transaction, not our own pending
+	// write, so that a blind overwrite-then-read-back doesn't spuriously
+	// conflict with itself at commit time
+	fn record(&mut self, key: &K) {
+		if let Some((snap, reads)) = &mut self.reads {
+			let base = snap.get(key).cloned();
+			reads.entry(key.clone()).or_insert(base);
+		}
+	}
+	/// Position the cursor at the first key greater than or equal to `key`,
+	/// clamped to the cursor's range, and return that entry
+	pub fn seek(&mut self, key: K) -> Option<(K, V)> {
+		let lo = match &self.lo {
+			Bound::Included(l) if *l > key => Bound::Included(l.clone()),
+			Bound::Excluded(l) if *l >= key => Bound::Excluded(l.clone()),
+			_ => Bound::Included(key),
+		};
+		let res = self.ds.range((lo, self.hi.clone())).next().map(|(k, v)| (k.clone(), v.clone()));
+		self.pos = match &res {
+			Some((k, _)) => Pos::At(k.clone()),
+			None => Pos::After,
+		};
+		if let Some((k, _)) = &res {
+			self.record(k);
+		}
+		res
+	}
+	/// Step forward to the next entry in the cursor's range
+	pub fn next(&mut self) -> Option<(K, V)> {
+		let lo = match &self.pos {
+			Pos::New | Pos::Before => self.lo.clone(),
+			Pos::At(k) => Bound::Excluded(k.clone()),
+			Pos::After => return None,
+		};
+		let res = self.ds.range((lo, self.hi.clone())).next().map(|(k, v)| (k.clone(), v.clone()));
+		self.pos = match &res {
+			Some((k, _)) => Pos::At(k.clone()),
+			None => Pos::After,
+		};
+		if let Some((k, _)) = &res {
+			self.record(k);
+		}
+		res
+	}
+	/// Step backward to the previous entry in the cursor's range
+	pub fn prev(&mut self) -> Option<(K, V)> {
+		let hi = match &self.pos {
+			Pos::New | Pos::After => self.hi.clone(),
+			Pos::At(k) => Bound::Excluded(k.clone()),
+			Pos::Before => return None,
+		};
+		let res =
+			self.ds.range((self.lo.clone(), hi)).next_back().map(|(k, v)| (k.clone(), v.clone()));
+		self.pos = match &res {
+			Some((k, _)) => Pos::At(k.clone()),
+			None => Pos::Before,
+		};
+		if let Some((k, _)) = &res {
+			self.record(k);
+		}
+		res
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Cursor;
+	use imbl::OrdMap;
+
+	fn sample() -> OrdMap<i32, i32> {
+		let mut m = OrdMap::new();
+		m.insert(1, 10);
+		m.insert(2, 20);
+		m.insert(3, 30);
+		m
+	}
+
+	#[test]
+	fn next_stops_at_the_end_instead_of_wrapping() {
+		let ds = sample();
+		let mut cursor = Cursor::new(&ds, ..);
+		let seen: Vec<_> = (0..5).map(|_| cursor.next()).collect();
+		assert_eq!(
+			seen,
+			vec![Some((1, 10)), Some((2, 20)), Some((3, 30)), None, None]
+		);
+	}
+
+	#[test]
+	fn prev_stops_at_the_start_instead_of_wrapping() {
+		let ds = sample();
+		let mut cursor = Cursor::new(&ds, ..);
+		let seen: Vec<_> = (0..5).map(|_| cursor.prev()).collect();
+		assert_eq!(
+			seen,
+			vec![Some((3, 30)), Some((2, 20)), Some((1, 10)), None, None]
+		);
+	}
+
+	#[test]
+	fn prev_as_the_first_call_returns_the_last_entry() {
+		let ds = sample();
+		let mut cursor = Cursor::new(&ds, ..);
+		assert_eq!(cursor.prev(), Some((3, 30)));
+	}
+
+	#[test]
+	fn seek_positions_at_first_key_greater_or_equal() {
+		let ds = sample();
+		let mut cursor = Cursor::new(&ds, ..);
+		assert_eq!(cursor.seek(2), Some((2, 20)));
+		assert_eq!(cursor.next(), Some((3, 30)));
+		assert_eq!(cursor.next(), None);
+	}
+}
@@ -0,0 +1,230 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module stores the top-level database handle.
+
+use crate::cnf::{Config, PersistType};
+use crate::err::Error;
+use crate::log::{CommitLog, LogCodec};
+use crate::obs::{Change, Observers};
+use crate::ser::Serializer;
+use crate::tx::{KeyspaceMap, Keyspaces, Persist, Tx, DEFAULT};
+use arc_swap::ArcSwap;
+use imbl::OrdMap;
+use std::fs;
+use std::ops::RangeBounds;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// An in-memory, optionally file-backed, database
+///
+/// A database may hold any number of named keyspaces; a single transaction
+/// can open several of them and commit its changes to all of them
+/// atomically. See [`Tx`].
+pub struct Db<K, V> {
+	// The registry of named keyspaces belonging to this database
+	keyspaces: Keyspaces<K, V>,
+	// The database-wide write lock; writers only take this briefly, around
+	// the validate-and-store step of their commit, not for their whole
+	// lifetime, so writers otherwise run fully concurrently with each other
+	lock: Arc<Mutex<()>>,
+	// The on-disk persistence configuration for the default keyspace, if any
+	persist: Option<Arc<Persist<K, V>>>,
+	// The write-ahead commit log for the default keyspace, if any
+	log: Option<Arc<CommitLog<K, V>>>,
+	// The registry of observers subscribed to change notifications
+	observers: Arc<Observers<K, V>>,
+}
+
+impl<K, V> Db<K, V>
+where
+	K: Ord + Clone,
+	V: Eq + Clone,
+{
+	/// Open a new database using the given configuration
+	///
+	/// Persistence, the commit log, and observers all apply to the default
+	/// keyspace; any other keyspaces opened later are purely in-memory.
+	///
+	/// A `ser` must be supplied whenever `config.persist` is
+	/// [`PersistType::File`], so the backing file can be read back into
+	/// memory on open, and written out again on commit or compaction. A
+	/// `log_codec` must additionally be supplied whenever `config.log` is
+	/// set, so individual commits can be appended to the commit log instead
+	/// of rewriting the whole snapshot.
+	pub fn new(
+		config: Config,
+		ser: Option<Box<dyn Serializer<K, V>>>,
+		log_codec: Option<Box<dyn LogCodec<K, V>>>,
+	) -> Result<Db<K, V>, Error> {
+		let persist = match config.persist {
+			PersistType::Memory => None,
+			PersistType::File(path) => {
+				let ser = ser.ok_or(Error::NoSerializer)?;
+				Some(Arc::new(Persist {
+					path,
+					sync: config.sync,
+					ser,
+					synced: Mutex::new(Instant::now()),
+				}))
+			}
+		};
+		// Load the initial data map from the backing snapshot, if one exists
+		let initial = match &persist {
+			Some(persist) => match fs::read(&persist.path) {
+				Ok(bytes) => persist.ser.decode(&bytes)?,
+				Err(e) if e.kind() == std::io::ErrorKind::NotFound => OrdMap::new(),
+				Err(e) => return Err(Error::Io(e.to_string())),
+			},
+			None => OrdMap::new(),
+		};
+		// Replay the commit log on top of the snapshot, if one is configured
+		let (log, initial) = match config.log {
+			Some(path) => {
+				let codec = log_codec.ok_or(Error::NoLogCodec)?;
+				let (log, initial) = CommitLog::open(path, codec, initial)?;
+				(Some(Arc::new(log)), initial)
+			}
+			None => (None, initial),
+		};
+		// Seed the registry with the default keyspace
+		let mut keyspaces = KeyspaceMap::new();
+		keyspaces.insert(DEFAULT.to_string(), initial);
+		Ok(Db {
+			keyspaces: Arc::new(ArcSwap::from_pointee(keyspaces)),
+			lock: Arc::new(Mutex::new(())),
+			persist,
+			log,
+			observers: Arc::new(Observers::default()),
+		})
+	}
+	/// Start a new read-only or writeable transaction
+	///
+	/// Keyspaces are opened lazily on the returned transaction as they are
+	/// first used. Opening a keyspace that doesn't exist yet only creates it
+	/// in the database if the transaction goes on to write to it and commit
+	/// successfully; a read-only open, or one later cancelled, never does.
+	/// Writeable transactions run lock-free until they commit.
+	pub async fn begin(&self, write: bool) -> Tx<K, V> {
+		Tx::new(
+			self.keyspaces.clone(),
+			self.lock.clone(),
+			write,
+			self.persist.clone(),
+			self.log.clone(),
+			self.observers.clone(),
+		)
+	}
+	/// Fold the default keyspace's current data map into a new base
+	/// snapshot, and truncate the commit log
+	///
+	/// This takes the same database-wide write lock as [`Tx::commit`], so
+	/// that compaction can never interleave with an in-flight commit: without
+	/// it, a commit could store its new map and append to the log after
+	/// compaction had already read the (now stale) map but before compaction
+	/// truncated the log, silently losing that commit from both the new
+	/// snapshot and the log.
+	pub fn compact(&self) -> Result<(), Error> {
+		let (persist, log) = match (&self.persist, &self.log) {
+			(Some(persist), Some(log)) => (persist, log),
+			_ => return Ok(()),
+		};
+		let _guard = self.lock.lock().unwrap();
+		let keyspaces = self.keyspaces.load();
+		let ds = keyspaces.get(DEFAULT).cloned().unwrap_or_else(OrdMap::new);
+		log.compact(&persist.path, persist.ser.as_ref(), &ds)
+	}
+	/// Subscribe to change notifications for commits affecting `range` in
+	/// the default keyspace
+	///
+	/// Only writeable transactions that commit deliver notifications; the
+	/// inserted, updated and deleted entries within `range` are sent over
+	/// the returned channel as each commit completes.
+	pub fn observe(&self, range: impl RangeBounds<K>) -> UnboundedReceiver<Change<K, V>> {
+		let (tx, rx) = mpsc::unbounded_channel();
+		self.observers.subscribe(range, tx);
+		rx
+	}
+}
+
+#[cfg(all(test, feature = "bincode"))]
+mod tests {
+	use super::Db;
+	use crate::cnf::{Config, PersistType, SyncPolicy};
+	use crate::ser::Bincode;
+	use crate::tx::DEFAULT;
+
+	// Removes the backing file on drop, so a failed assertion doesn't leave
+	// a stray file behind in the system temp directory
+	struct CleanupOnDrop(std::path::PathBuf);
+
+	impl Drop for CleanupOnDrop {
+		fn drop(&mut self) {
+			let _ = std::fs::remove_file(&self.0);
+		}
+	}
+
+	#[tokio::test]
+	async fn commits_are_persisted_and_reloaded_on_reopen() {
+		let path = std::env::temp_dir()
+			.join(format!("echodb-test-{}-{}.db", std::process::id(), line!()));
+		let _cleanup = CleanupOnDrop(path.clone());
+		let config = || Config {
+			persist: PersistType::File(path.clone()),
+			sync: SyncPolicy::Always,
+			log: None,
+		};
+		let db: Db<String, String> = Db::new(config(), Some(Box::new(Bincode)), None).unwrap();
+		let mut tx = db.begin(true).await;
+		tx.set(DEFAULT, "a".to_string(), "1".to_string()).unwrap();
+		tx.commit().unwrap();
+		// Reopening the database should read the persisted snapshot back
+		let db: Db<String, String> = Db::new(config(), Some(Box::new(Bincode)), None).unwrap();
+		let mut tx = db.begin(false).await;
+		assert_eq!(tx.get(DEFAULT, "a".to_string()).unwrap(), Some("1".to_string()));
+		tx.cancel().unwrap();
+	}
+}
+
+#[cfg(test)]
+mod notify_tests {
+	use super::Db;
+	use crate::cnf::Config;
+	use crate::obs::Change;
+	use crate::tx::DEFAULT;
+
+	#[tokio::test]
+	async fn a_cancelled_transaction_never_notifies() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		let mut rx = db.observe(..);
+		let mut tx = db.begin(true).await;
+		tx.set(DEFAULT, "a".to_string(), 1).unwrap();
+		tx.cancel().unwrap();
+		assert!(rx.try_recv().is_err());
+	}
+
+	#[tokio::test]
+	async fn a_committed_write_notifies_observers_within_range() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		let mut rx = db.observe("b".to_string()..);
+		let mut tx = db.begin(true).await;
+		// "a" is outside the observed range and must not be reported
+		tx.set(DEFAULT, "a".to_string(), 1).unwrap();
+		tx.set(DEFAULT, "b".to_string(), 2).unwrap();
+		tx.commit().unwrap();
+		assert_eq!(rx.recv().await, Some(Change::Insert("b".to_string(), 2)));
+		assert!(rx.try_recv().is_err());
+	}
+}
@@ -0,0 +1,49 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module stores the database error types.
+
+use thiserror::Error;
+
+/// An error originating from the database
+#[derive(Error, Debug)]
+pub enum Error {
+	/// The transaction has already been closed
+	#[error("transaction is closed")]
+	TxClosed,
+	/// The current transaction is read only
+	#[error("transaction is read only")]
+	TxNotWritable,
+	/// The key being inserted already exists
+	#[error("key already exists")]
+	KeyAlreadyExists,
+	/// The value being checked did not match the expected value
+	#[error("value being checked did not match the expected value")]
+	ValNotExpectedValue,
+	/// The transaction conflicted with another writer and could not commit
+	#[error("transaction conflicted with a concurrent writer")]
+	TxConflict,
+	/// An I/O error occurred while reading or writing the backing file
+	#[error("an I/O error occurred: {0}")]
+	Io(String),
+	/// The database map could not be encoded or decoded
+	#[error("failed to (de)serialize the database: {0}")]
+	Serialization(String),
+	/// A file-backed database was opened without a serializer
+	#[error("a serializer is required to persist the database to a file")]
+	NoSerializer,
+	/// A log-backed database was opened without a log codec
+	#[error("a log codec is required to persist the database to a commit log")]
+	NoLogCodec,
+}
@@ -0,0 +1,40 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! EchoDB is an embedded, in-memory, immutable, copy-on-write key-value
+//! database for Rust.
+
+mod cnf;
+mod cursor;
+mod db;
+mod err;
+mod log;
+mod obs;
+mod ser;
+mod tx;
+
+pub use cnf::{Config, PersistType, SyncPolicy};
+pub use cursor::Cursor;
+pub use db::Db;
+pub use err::Error;
+pub use log::{LogCodec, TxOp};
+pub use obs::Change;
+pub use ser::Serializer;
+#[cfg(feature = "bincode")]
+pub use ser::Bincode;
+#[cfg(feature = "json")]
+pub use ser::Json;
+#[cfg(feature = "ron")]
+pub use ser::Ron;
+pub use tx::{Tx, DEFAULT};
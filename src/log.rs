@@ -0,0 +1,192 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module stores the append-only write-ahead commit log.
+
+use crate::err::Error;
+use crate::ser::Serializer;
+use imbl::OrdMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A single mutation recorded in the commit log
+#[cfg_attr(
+	any(feature = "bincode", feature = "json", feature = "ron"),
+	derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TxOp<K, V> {
+	/// Set a key to a new value
+	Set(K, V),
+	/// Delete a key
+	Delete(K),
+}
+
+/// Encodes and decodes commit-log entries
+pub trait LogCodec<K, V>: Send + Sync {
+	/// Encode one transaction's operations, tagged with its commit sequence
+	fn encode(&self, seq: u64, ops: &[TxOp<K, V>]) -> Result<Vec<u8>, Error>;
+	/// Decode one entry back into its commit sequence and operations
+	fn decode(&self, bytes: &[u8]) -> Result<(u64, Vec<TxOp<K, V>>), Error>;
+}
+
+/// An append-only log of committed transactions, with replay and compaction
+pub(crate) struct CommitLog<K, V> {
+	// The file that log entries are appended to
+	path: PathBuf,
+	// The encoding used for each log entry
+	codec: Box<dyn LogCodec<K, V>>,
+	// The sequence number assigned to the most recently appended entry
+	seq: AtomicU64,
+}
+
+impl<K, V> CommitLog<K, V>
+where
+	K: Ord + Clone,
+	V: Clone,
+{
+	/// Open (or create) the commit log at `path`, replaying any entries it
+	/// already contains on top of `base` to reconstruct the data map
+	pub(crate) fn open(
+		path: PathBuf,
+		codec: Box<dyn LogCodec<K, V>>,
+		base: OrdMap<K, V>,
+	) -> Result<(CommitLog<K, V>, OrdMap<K, V>), Error> {
+		let mut ds = base;
+		let mut seq = 0;
+		if let Ok(bytes) = fs::read(&path) {
+			let mut rest = &bytes[..];
+			while rest.len() >= 4 {
+				let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+				rest = &rest[4..];
+				if rest.len() < len {
+					break;
+				}
+				let (entry, tail) = rest.split_at(len);
+				let (entry_seq, ops) = codec.decode(entry)?;
+				for op in ops {
+					match op {
+						TxOp::Set(k, v) => {
+							ds.insert(k, v);
+						}
+						TxOp::Delete(k) => {
+							ds.remove(&k);
+						}
+					}
+				}
+				seq = entry_seq;
+				rest = tail;
+			}
+		}
+		Ok((
+			CommitLog {
+				path,
+				codec,
+				seq: AtomicU64::new(seq),
+			},
+			ds,
+		))
+	}
+	/// Append a transaction's operations to the log, returning the commit
+	/// sequence number assigned to it
+	pub(crate) fn append(&self, ops: &[TxOp<K, V>]) -> Result<u64, Error> {
+		// Assign the next monotonically increasing commit sequence number
+		let seq = self.seq.fetch_add(1, Ordering::SeqCst) + 1;
+		let entry = self.codec.encode(seq, ops)?;
+		let mut file = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&self.path)
+			.map_err(|e| Error::Io(e.to_string()))?;
+		file.write_all(&(entry.len() as u32).to_le_bytes()).map_err(|e| Error::Io(e.to_string()))?;
+		file.write_all(&entry).map_err(|e| Error::Io(e.to_string()))?;
+		Ok(seq)
+	}
+	/// Fold `ds` into a new base snapshot at `snapshot`, and truncate the log
+	pub(crate) fn compact(
+		&self,
+		snapshot: &Path,
+		ser: &dyn Serializer<K, V>,
+		ds: &OrdMap<K, V>,
+	) -> Result<(), Error> {
+		// Write the new base snapshot alongside the existing one
+		let bytes = ser.encode(ds)?;
+		let tmp = snapshot.with_extension("tmp");
+		fs::write(&tmp, bytes).map_err(|e| Error::Io(e.to_string()))?;
+		fs::rename(&tmp, snapshot).map_err(|e| Error::Io(e.to_string()))?;
+		// The base snapshot now covers every entry, so truncate the log
+		fs::write(&self.path, []).map_err(|e| Error::Io(e.to_string()))?;
+		Ok(())
+	}
+}
+
+#[cfg(all(test, feature = "bincode"))]
+mod tests {
+	use super::CommitLog;
+	use crate::ser::{Bincode, Serializer};
+	use imbl::OrdMap;
+
+	// Removes the backing file on drop, so a failed assertion doesn't leave
+	// a stray file behind in the system temp directory
+	struct CleanupOnDrop(std::path::PathBuf);
+
+	impl Drop for CleanupOnDrop {
+		fn drop(&mut self) {
+			let _ = std::fs::remove_file(&self.0);
+		}
+	}
+
+	fn temp_path(name: &str) -> std::path::PathBuf {
+		std::env::temp_dir().join(format!("echodb-test-{}-{}", std::process::id(), name))
+	}
+
+	#[test]
+	fn replay_reconstructs_the_map_from_appended_entries() {
+		let path = temp_path("log-replay.log");
+		let _cleanup = CleanupOnDrop(path.clone());
+		let (log, ds) =
+			CommitLog::<String, i32>::open(path.clone(), Box::new(Bincode), OrdMap::new()).unwrap();
+		log.append(&[super::TxOp::Set("a".to_string(), 1)]).unwrap();
+		log.append(&[super::TxOp::Set("b".to_string(), 2), super::TxOp::Delete("a".to_string())])
+			.unwrap();
+		drop((log, ds));
+		// Reopening the log should replay both entries on top of an empty base
+		let (_log, ds) =
+			CommitLog::<String, i32>::open(path, Box::new(Bincode), OrdMap::new()).unwrap();
+		assert_eq!(ds.get("a"), None);
+		assert_eq!(ds.get("b"), Some(&2));
+	}
+
+	#[test]
+	fn compact_folds_the_log_into_the_snapshot_and_truncates_it() {
+		let log_path = temp_path("log-compact.log");
+		let snapshot_path = temp_path("log-compact.db");
+		let _cleanup_log = CleanupOnDrop(log_path.clone());
+		let _cleanup_snapshot = CleanupOnDrop(snapshot_path.clone());
+		let (log, _) =
+			CommitLog::<String, i32>::open(log_path.clone(), Box::new(Bincode), OrdMap::new())
+				.unwrap();
+		log.append(&[super::TxOp::Set("a".to_string(), 1)]).unwrap();
+		let mut ds = OrdMap::new();
+		ds.insert("a".to_string(), 1);
+		log.compact(&snapshot_path, &Bincode, &ds).unwrap();
+		// The log is now empty, but replaying it on top of the fresh snapshot
+		// still yields the compacted state
+		let snapshot = Bincode.decode(&std::fs::read(&snapshot_path).unwrap()).unwrap();
+		let (_log, ds) = CommitLog::<String, i32>::open(log_path, Box::new(Bincode), snapshot).unwrap();
+		assert_eq!(ds.get("a"), Some(&1));
+	}
+}
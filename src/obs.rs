@@ -0,0 +1,157 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module stores the transaction observer registry used to deliver
+//! change notifications on commit.
+
+use imbl::OrdMap;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Mutex;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A single change delivered to an observer on commit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<K, V> {
+	/// A key that did not exist before was inserted
+	Insert(K, V),
+	/// A key that already existed was updated with a new value
+	Update(K, V),
+	/// A key that existed before was deleted
+	Delete(K),
+}
+
+// An observer registered against a key range of interest
+struct Observer<K, V> {
+	range: (Bound<K>, Bound<K>),
+	tx: UnboundedSender<Change<K, V>>,
+}
+
+/// The registry of observers for a single database
+pub(crate) struct Observers<K, V> {
+	inner: Mutex<Vec<Observer<K, V>>>,
+}
+
+impl<K, V> Default for Observers<K, V> {
+	fn default() -> Self {
+		Observers {
+			inner: Mutex::new(Vec::new()),
+		}
+	}
+}
+
+impl<K, V> Observers<K, V>
+where
+	K: Ord + Clone,
+	V: Eq + Clone,
+{
+	/// Register a new observer against the given key range
+	pub(crate) fn subscribe(
+		&self,
+		range: impl RangeBounds<K>,
+		tx: UnboundedSender<Change<K, V>>,
+	) {
+		let range = (range.start_bound().cloned(), range.end_bound().cloned());
+		self.inner.lock().unwrap().push(Observer {
+			range,
+			tx,
+		});
+	}
+	/// Diff `old` against `new` for every registered observer, and deliver
+	/// the affected entries within each observer's range
+	pub(crate) fn notify(&self, old: &OrdMap<K, V>, new: &OrdMap<K, V>) {
+		// Drop observers whose receiver has gone away
+		self.inner.lock().unwrap().retain(|observer| {
+			for change in diff(old, new, &observer.range) {
+				if observer.tx.send(change).is_err() {
+					return false;
+				}
+			}
+			true
+		});
+	}
+}
+
+// Compute the ordered diff between `old` and `new`, restricted to `range`
+fn diff<K, V>(old: &OrdMap<K, V>, new: &OrdMap<K, V>, range: &(Bound<K>, Bound<K>)) -> Vec<Change<K, V>>
+where
+	K: Ord + Clone,
+	V: Eq + Clone,
+{
+	let mut out = Vec::new();
+	let mut oi = old.range(range.clone()).peekable();
+	let mut ni = new.range(range.clone()).peekable();
+	loop {
+		match (oi.peek(), ni.peek()) {
+			(Some((ok, _)), Some((nk, _))) => match ok.cmp(nk) {
+				Ordering::Less => {
+					out.push(Change::Delete((*ok).clone()));
+					oi.next();
+				}
+				Ordering::Greater => {
+					let (nk, nv) = ni.next().unwrap();
+					out.push(Change::Insert(nk.clone(), nv.clone()));
+				}
+				Ordering::Equal => {
+					let (ok, ov) = oi.next().unwrap();
+					let (_, nv) = ni.next().unwrap();
+					if ov != nv {
+						out.push(Change::Update(ok.clone(), nv.clone()));
+					}
+				}
+			},
+			(Some((ok, _)), None) => {
+				out.push(Change::Delete((*ok).clone()));
+				oi.next();
+			}
+			(None, Some((nk, nv))) => {
+				out.push(Change::Insert((*nk).clone(), (*nv).clone()));
+				ni.next();
+			}
+			(None, None) => break,
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{diff, Change};
+	use imbl::OrdMap;
+	use std::ops::Bound;
+
+	fn map(entries: &[(i32, i32)]) -> OrdMap<i32, i32> {
+		entries.iter().cloned().collect()
+	}
+
+	#[test]
+	fn diff_reports_inserts_updates_and_deletes() {
+		let old = map(&[(1, 10), (2, 20), (3, 30)]);
+		let new = map(&[(2, 25), (3, 30), (4, 40)]);
+		let range = (Bound::Unbounded, Bound::Unbounded);
+		assert_eq!(
+			diff(&old, &new, &range),
+			vec![Change::Delete(1), Change::Update(2, 25), Change::Insert(4, 40)]
+		);
+	}
+
+	#[test]
+	fn diff_is_restricted_to_the_observer_range() {
+		let old = map(&[(1, 10), (2, 20)]);
+		let new = map(&[(1, 11), (2, 20)]);
+		let range = (Bound::Included(2), Bound::Unbounded);
+		// The change to key 1 is outside the range, so it's not reported
+		assert_eq!(diff(&old, &new, &range), Vec::new());
+	}
+}
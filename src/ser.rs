@@ -0,0 +1,139 @@
+// Copyright © SurrealDB Ltd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module stores the pluggable on-disk encodings used for persistence.
+
+use crate::err::Error;
+use imbl::OrdMap;
+
+/// Encodes and decodes a database map for on-disk persistence
+///
+/// Implementations are chosen at database-open time, so the `K: Serialize`
+/// and `V: Serialize` bounds required by a particular encoding only apply to
+/// callers that opt in to file-backed persistence.
+pub trait Serializer<K, V>: Send + Sync {
+	/// Encode the map into bytes to be written to the backing file
+	fn encode(&self, map: &OrdMap<K, V>) -> Result<Vec<u8>, Error>;
+	/// Decode bytes read from the backing file back into a map
+	fn decode(&self, bytes: &[u8]) -> Result<OrdMap<K, V>, Error>;
+}
+
+/// A [`Serializer`] backed by the `bincode` crate
+#[cfg(feature = "bincode")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl<K, V> Serializer<K, V> for Bincode
+where
+	K: Ord + Clone + serde::Serialize + serde::de::DeserializeOwned,
+	V: Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn encode(&self, map: &OrdMap<K, V>) -> Result<Vec<u8>, Error> {
+		let entries: Vec<(&K, &V)> = map.iter().collect();
+		bincode::serialize(&entries).map_err(|e| Error::Serialization(e.to_string()))
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<OrdMap<K, V>, Error> {
+		let entries: Vec<(K, V)> =
+			bincode::deserialize(bytes).map_err(|e| Error::Serialization(e.to_string()))?;
+		Ok(entries.into_iter().collect())
+	}
+}
+
+/// A [`Serializer`] backed by `serde_json`
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl<K, V> Serializer<K, V> for Json
+where
+	K: Ord + Clone + serde::Serialize + serde::de::DeserializeOwned,
+	V: Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn encode(&self, map: &OrdMap<K, V>) -> Result<Vec<u8>, Error> {
+		let entries: Vec<(&K, &V)> = map.iter().collect();
+		serde_json::to_vec(&entries).map_err(|e| Error::Serialization(e.to_string()))
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<OrdMap<K, V>, Error> {
+		let entries: Vec<(K, V)> =
+			serde_json::from_slice(bytes).map_err(|e| Error::Serialization(e.to_string()))?;
+		Ok(entries.into_iter().collect())
+	}
+}
+
+/// A [`Serializer`] backed by `ron`
+#[cfg(feature = "ron")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ron;
+
+#[cfg(feature = "ron")]
+impl<K, V> Serializer<K, V> for Ron
+where
+	K: Ord + Clone + serde::Serialize + serde::de::DeserializeOwned,
+	V: Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn encode(&self, map: &OrdMap<K, V>) -> Result<Vec<u8>, Error> {
+		let entries: Vec<(&K, &V)> = map.iter().collect();
+		ron::to_string(&entries).map(String::into_bytes).map_err(|e| Error::Serialization(e.to_string()))
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<OrdMap<K, V>, Error> {
+		let text = std::str::from_utf8(bytes).map_err(|e| Error::Serialization(e.to_string()))?;
+		let entries: Vec<(K, V)> = ron::from_str(text).map_err(|e| Error::Serialization(e.to_string()))?;
+		Ok(entries.into_iter().collect())
+	}
+}
+
+#[cfg(feature = "bincode")]
+impl<K, V> crate::log::LogCodec<K, V> for Bincode
+where
+	K: Ord + Clone + serde::Serialize + serde::de::DeserializeOwned,
+	V: Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn encode(&self, seq: u64, ops: &[crate::log::TxOp<K, V>]) -> Result<Vec<u8>, Error> {
+		bincode::serialize(&(seq, ops)).map_err(|e| Error::Serialization(e.to_string()))
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<(u64, Vec<crate::log::TxOp<K, V>>), Error> {
+		bincode::deserialize(bytes).map_err(|e| Error::Serialization(e.to_string()))
+	}
+}
+
+#[cfg(feature = "json")]
+impl<K, V> crate::log::LogCodec<K, V> for Json
+where
+	K: Ord + Clone + serde::Serialize + serde::de::DeserializeOwned,
+	V: Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn encode(&self, seq: u64, ops: &[crate::log::TxOp<K, V>]) -> Result<Vec<u8>, Error> {
+		serde_json::to_vec(&(seq, ops)).map_err(|e| Error::Serialization(e.to_string()))
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<(u64, Vec<crate::log::TxOp<K, V>>), Error> {
+		serde_json::from_slice(bytes).map_err(|e| Error::Serialization(e.to_string()))
+	}
+}
+
+#[cfg(feature = "ron")]
+impl<K, V> crate::log::LogCodec<K, V> for Ron
+where
+	K: Ord + Clone + serde::Serialize + serde::de::DeserializeOwned,
+	V: Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn encode(&self, seq: u64, ops: &[crate::log::TxOp<K, V>]) -> Result<Vec<u8>, Error> {
+		ron::to_string(&(seq, ops)).map(String::into_bytes).map_err(|e| Error::Serialization(e.to_string()))
+	}
+	fn decode(&self, bytes: &[u8]) -> Result<(u64, Vec<crate::log::TxOp<K, V>>), Error> {
+		let text = std::str::from_utf8(bytes).map_err(|e| Error::Serialization(e.to_string()))?;
+		ron::from_str(text).map_err(|e| Error::Serialization(e.to_string()))
+	}
+}
@@ -14,26 +14,91 @@
 
 //! This module stores the database transaction logic.
 
+use crate::cnf::SyncPolicy;
+use crate::cursor::Cursor;
 use crate::err::Error;
+use crate::log::{CommitLog, TxOp};
+use crate::obs::Observers;
+use crate::ser::Serializer;
 use arc_swap::ArcSwap;
-use imbl::ordmap::Entry;
 use imbl::OrdMap;
-use std::ops::Range;
-use std::sync::Arc;
-use tokio::sync::OwnedMutexGuard;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::ops::RangeBounds;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// The name of the keyspace used when one isn't otherwise specified
+///
+/// Persistence, the commit log, and observers are only ever wired up
+/// against this keyspace, so a single-keyspace database behaves exactly as
+/// it did before named keyspaces existed.
+pub const DEFAULT: &str = "default";
+
+/// The on-disk persistence configuration shared by every transaction
+/// started against a file-backed database
+pub(crate) struct Persist<K, V> {
+	// The backing file that the database map is persisted to
+	pub(crate) path: PathBuf,
+	// When a commit should be flushed to the backing file
+	pub(crate) sync: SyncPolicy,
+	// The encoding used to read and write the backing file
+	pub(crate) ser: Box<dyn Serializer<K, V>>,
+	// The instant the backing file was last flushed, used by `SyncPolicy::Every`
+	pub(crate) synced: Mutex<Instant>,
+}
+
+/// The data maps of every named keyspace belonging to a single database
+///
+/// This is stored as a single persistent map so that committing changes to
+/// several keyspaces at once is a single atomic swap: a reader can never
+/// observe one keyspace updated while another, modified by the same
+/// transaction, still shows its old value.
+pub(crate) type KeyspaceMap<K, V> = imbl::HashMap<String, OrdMap<K, V>>;
+
+/// The registry of keyspaces belonging to a single database
+pub(crate) type Keyspaces<K, V> = Arc<ArcSwap<KeyspaceMap<K, V>>>;
+
+// The working state of a single keyspace, opened within a transaction
+struct Space<K, V> {
+	// The snapshot of the keyspace this transaction was started from
+	snap: OrdMap<K, V>,
+	// The immutable working copy of the keyspace's data map
+	ds: OrdMap<K, V>,
+	// The keys read from this keyspace, and the value observed at read time
+	reads: BTreeMap<K, Option<V>>,
+	// The keys written to this keyspace, and the value to apply at commit
+	writes: BTreeMap<K, Option<V>>,
+}
 
 /// A serializable database transaction
+///
+/// A transaction may open any number of named keyspaces, each of which is
+/// snapshotted independently. All keyspaces opened by a transaction commit
+/// (or conflict) together, as a single atomic swap of the whole keyspace
+/// registry, so mutations to several keyspaces are applied atomically and
+/// are never visible to a reader half-applied.
 pub struct Tx<K, V> {
 	// Is the transaction complete?
-	pub(crate) ok: bool,
+	ok: bool,
 	// Is the transaction read+write?
-	pub(crate) rw: bool,
-	// The immutable copy of the data map
-	pub(crate) ds: OrdMap<K, V>,
-	// The pointer to the latest data map
-	pub(crate) pt: Arc<ArcSwap<OrdMap<K, V>>>,
-	// The underlying database write mutex
-	pub(crate) lk: Option<OwnedMutexGuard<()>>,
+	rw: bool,
+	// The database-wide write lock, taken briefly at commit time to
+	// serialize the validate-and-store step against other writers
+	lock: Arc<Mutex<()>>,
+	// The registry of keyspaces belonging to the database
+	keyspaces: Keyspaces<K, V>,
+	// The registry snapshot this transaction was started from
+	base: Arc<KeyspaceMap<K, V>>,
+	// The keyspaces opened so far by this transaction, keyed by name
+	spaces: HashMap<String, Space<K, V>>,
+	// The on-disk persistence configuration, if the database is file-backed
+	persist: Option<Arc<Persist<K, V>>>,
+	// The write-ahead commit log, if the database is log-backed
+	log: Option<Arc<CommitLog<K, V>>>,
+	// The registry of observers to notify once this transaction commits
+	observers: Arc<Observers<K, V>>,
 }
 
 impl<K, V> Tx<K, V>
@@ -43,16 +108,24 @@ where
 {
 	/// Create a new read-only or writeable transaction
 	pub(crate) fn new(
-		pt: Arc<ArcSwap<OrdMap<K, V>>>,
+		keyspaces: Keyspaces<K, V>,
+		lock: Arc<Mutex<()>>,
 		write: bool,
-		guard: Option<OwnedMutexGuard<()>>,
+		persist: Option<Arc<Persist<K, V>>>,
+		log: Option<Arc<CommitLog<K, V>>>,
+		observers: Arc<Observers<K, V>>,
 	) -> Tx<K, V> {
+		let base = keyspaces.load_full();
 		Tx {
 			ok: false,
 			rw: write,
-			lk: guard,
-			pt: pt.clone(),
-			ds: (*(*pt.load())).clone(),
+			lock,
+			keyspaces,
+			base,
+			spaces: HashMap::new(),
+			persist,
+			log,
+			observers,
 		}
 	}
 	/// Check if the transaction is closed
@@ -65,17 +138,33 @@ where
 		if self.ok == true {
 			return Err(Error::TxClosed);
 		}
-		// Mark this transaction as done
+		// Mark this transaction as done; nothing was ever made visible to
+		// other transactions, so there is nothing left to roll back
 		self.ok = true;
-		// Unlock the database mutex
-		if let Some(lk) = self.lk.take() {
-			drop(lk);
-		}
 		// Continue
 		Ok(())
 	}
 	/// Commit the transaction and store all changes
-	pub fn commit(&mut self) -> Result<(), Error> {
+	///
+	/// Every keyspace this transaction wrote to is applied and swapped into
+	/// view together, as a single atomic update to the whole keyspace
+	/// registry: if any keyspace conflicts with a concurrent writer, the
+	/// whole commit is aborted and nothing becomes visible; if it succeeds,
+	/// every modified keyspace becomes visible to readers at once. Keyspaces
+	/// that were only opened for reading are never written back, so a
+	/// cancelled or read-only transaction can never create a keyspace.
+	///
+	/// Writers run lock-free for the whole lifetime of the transaction; the
+	/// database-wide write lock is only taken out here, briefly, around the
+	/// validate-and-store step. Validation uses optimistic concurrency
+	/// control: for each keyspace, it checks that every key this transaction
+	/// read from that keyspace is still unchanged in the latest committed
+	/// map, then applies its own write set on top of that newer map.
+	///
+	/// When the database is log-backed, returns the commit sequence number
+	/// assigned to the default keyspace's write set, so callers can build
+	/// change-feed tooling on top of it.
+	pub fn commit(&mut self) -> Result<Option<u64>, Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
@@ -86,39 +175,164 @@ where
 		}
 		// Mark this transaction as done
 		self.ok = true;
-		// Commit the data
-		self.pt.store(Arc::new(self.ds.clone()));
-		// Unlock the database mutex
-		if let Some(lk) = self.lk.take() {
-			drop(lk);
+		// Take the database-wide write lock for the validate-and-store step
+		// only; it is released automatically when this guard goes out of
+		// scope, on every return path below
+		let _guard = self.lock.lock().unwrap();
+		let current = self.keyspaces.load_full();
+		// If another writer has committed since this transaction's snapshot
+		// was taken, validate that every key we read is still unchanged
+		if !Arc::ptr_eq(&current, &self.base) {
+			for (name, space) in self.spaces.iter() {
+				let cur_ks = current.get(name.as_str()).cloned().unwrap_or_else(OrdMap::new);
+				for (key, val) in space.reads.iter() {
+					if cur_ks.get(key) != val.as_ref() {
+						return Err(Error::TxConflict);
+					}
+				}
+			}
+		}
+		// Apply every keyspace this transaction actually wrote to, on top of
+		// the latest committed value for that keyspace, and collect the
+		// result into a single new registry, so that it can be swapped into
+		// view as one atomic unit below
+		let mut next = (*current).clone();
+		for (name, space) in self.spaces.iter() {
+			if space.writes.is_empty() {
+				continue;
+			}
+			let mut ds = current.get(name.as_str()).cloned().unwrap_or_else(OrdMap::new);
+			for (key, val) in space.writes.iter() {
+				match val {
+					Some(val) => ds.insert(key.clone(), val.clone()),
+					None => ds.remove(key),
+				};
+			}
+			next.insert(name.clone(), ds);
+		}
+		let next = Arc::new(next);
+		// Every keyspace validated cleanly, so make them all visible at once
+		self.keyspaces.store(next.clone());
+		// Flush, notify, and log against the default keyspace, if it was touched
+		let seq = match self.spaces.get(DEFAULT) {
+			Some(space) if !space.writes.is_empty() => {
+				let old = current.get(DEFAULT).cloned().unwrap_or_else(OrdMap::new);
+				let new = next.get(DEFAULT).cloned().unwrap_or_else(OrdMap::new);
+				// Flush the newly committed map to the backing file, if configured
+				if let Some(persist) = &self.persist {
+					if Self::due(persist) {
+						Self::flush(persist, &new)?;
+					}
+				}
+				// Notify any observers whose range overlaps the committed changes
+				self.observers.notify(&old, &new);
+				// Append this transaction's write set to the commit log, if configured
+				match &self.log {
+					Some(log) => {
+						let ops: Vec<TxOp<K, V>> = space
+							.writes
+							.iter()
+							.map(|(k, v)| match v {
+								Some(v) => TxOp::Set(k.clone(), v.clone()),
+								None => TxOp::Delete(k.clone()),
+							})
+							.collect();
+						Some(log.append(&ops)?)
+					}
+					None => None,
+				}
+			}
+			_ => None,
+		};
+		// Continue
+		Ok(seq)
+	}
+	// Whether a commit should flush to the backing file now, according to
+	// the configured sync policy
+	fn due(persist: &Persist<K, V>) -> bool {
+		match persist.sync {
+			SyncPolicy::Always => true,
+			SyncPolicy::Never => false,
+			SyncPolicy::Every(interval) => persist.synced.lock().unwrap().elapsed() >= interval,
 		}
+	}
+	/// Serialize the given data map and atomically replace the backing file
+	fn flush(persist: &Persist<K, V>, ds: &OrdMap<K, V>) -> Result<(), Error> {
+		// Encode the map using the configured serializer
+		let bytes = persist.ser.encode(ds)?;
+		// Write to a temporary file alongside the backing file
+		let tmp = persist.path.with_extension("tmp");
+		fs::write(&tmp, bytes).map_err(|e| Error::Io(e.to_string()))?;
+		// Atomically replace the backing file with the temporary file
+		fs::rename(&tmp, &persist.path).map_err(|e| Error::Io(e.to_string()))?;
+		// Record when this flush happened, so `SyncPolicy::Every` knows when
+		// the next one is due
+		*persist.synced.lock().unwrap() = Instant::now();
 		// Continue
 		Ok(())
 	}
-	/// Check if a key exists in the database
-	pub fn exi(&self, key: K) -> Result<bool, Error> {
+	// Open a keyspace within this transaction, from the snapshot this
+	// transaction was started from
+	//
+	// This never touches the shared keyspace registry: a keyspace that
+	// doesn't exist yet is opened locally as an empty map, and only becomes
+	// a real keyspace in the database if this transaction goes on to write
+	// to it and commit successfully. A read-only transaction, or one that
+	// is cancelled, can therefore never create a keyspace, even if it opens
+	// one under a name that doesn't exist (for example, a typo).
+	fn space(&mut self, ks: &str) -> &mut Space<K, V> {
+		if !self.spaces.contains_key(ks) {
+			let snap = self.base.get(ks).cloned().unwrap_or_else(OrdMap::new);
+			self.spaces.insert(
+				ks.to_string(),
+				Space {
+					ds: snap.clone(),
+					snap,
+					reads: BTreeMap::new(),
+					writes: BTreeMap::new(),
+				},
+			);
+		}
+		self.spaces.get_mut(ks).unwrap()
+	}
+	/// Check if a key exists in the given keyspace
+	pub fn exi(&mut self, ks: &str, key: K) -> Result<bool, Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
 		}
-		// Check the key
-		let res = self.ds.contains_key(&key);
+		// Get the key
+		let space = self.space(ks);
+		let res = space.ds.contains_key(&key);
+		// Record the key in the read set for conflict detection, using the
+		// value observed at the start of the transaction, not our own
+		// pending write, so that a blind overwrite-then-read-back doesn't
+		// spuriously conflict with itself at commit time
+		let base = space.snap.get(&key).cloned();
+		space.reads.entry(key).or_insert(base);
 		// Return result
 		Ok(res)
 	}
-	/// Fetch a key from the database
-	pub fn get(&self, key: K) -> Result<Option<V>, Error> {
+	/// Fetch a key from the given keyspace
+	pub fn get(&mut self, ks: &str, key: K) -> Result<Option<V>, Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
 		}
 		// Get the key
-		let res = self.ds.get(&key).cloned();
+		let space = self.space(ks);
+		let res = space.ds.get(&key).cloned();
+		// Record the key in the read set for conflict detection, using the
+		// value observed at the start of the transaction, not our own
+		// pending write, so that a blind overwrite-then-read-back doesn't
+		// spuriously conflict with itself at commit time
+		let base = space.snap.get(&key).cloned();
+		space.reads.entry(key).or_insert(base);
 		// Return result
 		Ok(res)
 	}
-	/// Insert or update a key in the database
-	pub fn set(&mut self, key: K, val: V) -> Result<(), Error> {
+	/// Insert or update a key in the given keyspace
+	pub fn set(&mut self, ks: &str, key: K, val: V) -> Result<(), Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
@@ -128,12 +342,15 @@ where
 			return Err(Error::TxNotWritable);
 		}
 		// Set the key
-		self.ds.insert(key, val);
+		let space = self.space(ks);
+		space.ds.insert(key.clone(), val.clone());
+		// Record the write in the write set for conflict resolution
+		space.writes.insert(key, Some(val));
 		// Return result
 		Ok(())
 	}
-	/// Insert a key if it doesn't exist in the database
-	pub fn put(&mut self, key: K, val: V) -> Result<(), Error> {
+	/// Insert a key if it doesn't exist in the given keyspace
+	pub fn put(&mut self, ks: &str, key: K, val: V) -> Result<(), Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
@@ -143,15 +360,18 @@ where
 			return Err(Error::TxNotWritable);
 		}
 		// Set the key
-		match self.ds.contains_key(&key) {
-			false => self.ds.insert(key, val),
+		let space = self.space(ks);
+		match space.ds.contains_key(&key) {
+			false => space.ds.insert(key.clone(), val.clone()),
 			_ => return Err(Error::KeyAlreadyExists),
 		};
+		// Record the write in the write set for conflict resolution
+		space.writes.insert(key, Some(val));
 		// Return result
 		Ok(())
 	}
-	/// Insert a key if it matches a value
-	pub fn putc(&mut self, key: K, val: V, chk: Option<V>) -> Result<(), Error> {
+	/// Insert a key if it matches a value in the given keyspace
+	pub fn putc(&mut self, ks: &str, key: K, val: V, chk: Option<V>) -> Result<(), Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
@@ -161,16 +381,19 @@ where
 			return Err(Error::TxNotWritable);
 		}
 		// Set the key
-		match (self.ds.get(&key), &chk) {
-			(Some(v), Some(w)) if v == w => self.ds.insert(key, val),
-			(None, None) => self.ds.insert(key, val),
+		let space = self.space(ks);
+		match (space.ds.get(&key), &chk) {
+			(Some(v), Some(w)) if v == w => space.ds.insert(key.clone(), val.clone()),
+			(None, None) => space.ds.insert(key.clone(), val.clone()),
 			_ => return Err(Error::ValNotExpectedValue),
 		};
+		// Record the write in the write set for conflict resolution
+		space.writes.insert(key, Some(val));
 		// Return result
 		Ok(())
 	}
-	/// Delete a key from the database
-	pub fn del(&mut self, key: K) -> Result<(), Error> {
+	/// Delete a key from the given keyspace
+	pub fn del(&mut self, ks: &str, key: K) -> Result<(), Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
@@ -180,12 +403,15 @@ where
 			return Err(Error::TxNotWritable);
 		}
 		// Remove the key
-		self.ds.remove(&key);
+		let space = self.space(ks);
+		space.ds.remove(&key);
+		// Record the write in the write set for conflict resolution
+		space.writes.insert(key, None);
 		// Return result
 		Ok(())
 	}
-	/// Delete a key if it matches a value
-	pub fn delc(&mut self, key: K, chk: Option<V>) -> Result<(), Error> {
+	/// Delete a key if it matches a value in the given keyspace
+	pub fn delc(&mut self, ks: &str, key: K, chk: Option<V>) -> Result<(), Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
@@ -195,23 +421,184 @@ where
 			return Err(Error::TxNotWritable);
 		}
 		// Remove the key
-		match (self.ds.get(&key), &chk) {
-			(Some(v), Some(w)) if v == w => self.ds.remove(&key),
-			(None, None) => self.ds.remove(&key),
+		let space = self.space(ks);
+		match (space.ds.get(&key), &chk) {
+			(Some(v), Some(w)) if v == w => space.ds.remove(&key),
+			(None, None) => space.ds.remove(&key),
 			_ => return Err(Error::ValNotExpectedValue),
 		};
+		// Record the write in the write set for conflict resolution
+		space.writes.insert(key, None);
 		// Return result
 		Ok(())
 	}
-	/// Retrieve a range of keys from the databases
-	pub fn scan(&self, rng: Range<K>, limit: usize) -> Result<Vec<(K, V)>, Error> {
+	/// Retrieve a range of keys from the given keyspace
+	pub fn scan(
+		&mut self,
+		ks: &str,
+		rng: impl RangeBounds<K>,
+		limit: usize,
+	) -> Result<Vec<(K, V)>, Error> {
 		// Check to see if transaction is closed
 		if self.ok == true {
 			return Err(Error::TxClosed);
 		}
 		// Scan the keys
-		let res = self.ds.range(rng).take(limit).map(|(k, v)| (k.clone(), v.clone())).collect();
+		let space = self.space(ks);
+		let res: Vec<(K, V)> =
+			space.ds.range(rng).take(limit).map(|(k, v)| (k.clone(), v.clone())).collect();
+		// Record each key in the read set for conflict detection, using the
+		// value observed at the start of the transaction, not our own
+		// pending write, so that a blind overwrite-then-read-back doesn't
+		// spuriously conflict with itself at commit time
+		for (key, _) in res.iter() {
+			let base = space.snap.get(key).cloned();
+			space.reads.entry(key.clone()).or_insert(base);
+		}
 		// Return result
 		Ok(res)
 	}
+	/// Retrieve a range of keys from the given keyspace, in descending order
+	pub fn scan_rev(
+		&mut self,
+		ks: &str,
+		rng: impl RangeBounds<K>,
+		limit: usize,
+	) -> Result<Vec<(K, V)>, Error> {
+		// Check to see if transaction is closed
+		if self.ok == true {
+			return Err(Error::TxClosed);
+		}
+		// Scan the keys in reverse
+		let space = self.space(ks);
+		let res: Vec<(K, V)> =
+			space.ds.range(rng).rev().take(limit).map(|(k, v)| (k.clone(), v.clone())).collect();
+		// Record each key in the read set for conflict detection, using the
+		// value observed at the start of the transaction, not our own
+		// pending write, so that a blind overwrite-then-read-back doesn't
+		// spuriously conflict with itself at commit time
+		for (key, _) in res.iter() {
+			let base = space.snap.get(key).cloned();
+			space.reads.entry(key.clone()).or_insert(base);
+		}
+		// Return result
+		Ok(res)
+	}
+	/// Obtain a bidirectional cursor over a range of keys in the given keyspace
+	pub fn cursor(&mut self, ks: &str, rng: impl RangeBounds<K>) -> Result<Cursor<'_, K, V>, Error> {
+		// Check to see if transaction is closed
+		if self.ok == true {
+			return Err(Error::TxClosed);
+		}
+		// Return the cursor, tracking every key it yields in the read set
+		// for conflict detection, the same as `get`/`exi`/`scan`/`scan_rev`
+		let space = self.space(ks);
+		Ok(Cursor::tracked(&space.ds, rng, &space.snap, &mut space.reads))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::DEFAULT;
+	use crate::cnf::Config;
+	use crate::db::Db;
+	use crate::err::Error;
+
+	#[tokio::test]
+	async fn commit_conflicts_when_a_concurrently_modified_read_key_changed() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		let mut tx1 = db.begin(true).await;
+		let mut tx2 = db.begin(true).await;
+		// tx2 reads a key that tx1 is about to change
+		tx2.get(DEFAULT, "k".to_string()).unwrap();
+		tx1.set(DEFAULT, "k".to_string(), 1).unwrap();
+		tx1.commit().unwrap();
+		// tx2's own write is unrelated, but its read set is now stale
+		tx2.set(DEFAULT, "other".to_string(), 2).unwrap();
+		assert!(matches!(tx2.commit(), Err(Error::TxConflict)));
+	}
+
+	#[tokio::test]
+	async fn commit_does_not_conflict_on_a_blind_write_read_back() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		let mut tx1 = db.begin(true).await;
+		let mut tx2 = db.begin(true).await;
+		// tx2 writes a key and reads it straight back; this must be recorded
+		// against the value tx2 started from, not against its own pending
+		// write, or it will look like a dependency that was never satisfied
+		tx2.set(DEFAULT, "y".to_string(), 10).unwrap();
+		assert_eq!(tx2.get(DEFAULT, "y".to_string()).unwrap(), Some(10));
+		// tx1 commits an unrelated change, forcing tx2's commit onto the
+		// validation path instead of the fast, no-other-writer path
+		tx1.set(DEFAULT, "z".to_string(), 1).unwrap();
+		tx1.commit().unwrap();
+		assert!(tx2.commit().is_ok());
+	}
+
+	#[tokio::test]
+	async fn commit_applies_every_written_keyspace_together() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		let mut tx = db.begin(true).await;
+		tx.set("data", "k".to_string(), 1).unwrap();
+		tx.set("index", "k".to_string(), 100).unwrap();
+		tx.commit().unwrap();
+		// Both keyspaces became visible together, not one ahead of the other
+		let mut check = db.begin(false).await;
+		assert_eq!(check.get("data", "k".to_string()).unwrap(), Some(1));
+		assert_eq!(check.get("index", "k".to_string()).unwrap(), Some(100));
+	}
+
+	#[tokio::test]
+	async fn read_only_keyspace_open_never_creates_it() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		let mut reader = db.begin(false).await;
+		// A typo'd keyspace name read through a read-only transaction must
+		// not linger once that transaction is done with it
+		assert_eq!(reader.get("typo", "k".to_string()).unwrap(), None);
+		reader.cancel().unwrap();
+		assert!(!db.begin(false).await.base.contains_key("typo"));
+		// A writer opening the same name from scratch still starts empty,
+		// and committing to it is what actually creates the keyspace
+		let mut writer = db.begin(true).await;
+		assert_eq!(writer.get("typo", "k".to_string()).unwrap(), None);
+		writer.set("typo", "k".to_string(), 7).unwrap();
+		writer.commit().unwrap();
+		let mut check = db.begin(false).await;
+		assert_eq!(check.get("typo", "k".to_string()).unwrap(), Some(7));
+	}
+
+	#[tokio::test]
+	async fn commit_conflicts_when_a_concurrently_modified_cursor_key_changed() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		// Seed a key before either transaction starts
+		let mut seed = db.begin(true).await;
+		seed.set(DEFAULT, "k".to_string(), 0).unwrap();
+		seed.commit().unwrap();
+		let mut tx1 = db.begin(true).await;
+		let mut tx2 = db.begin(true).await;
+		// tx2 reads the key only through a cursor, never through `get`
+		{
+			let mut cursor = tx2.cursor(DEFAULT, ..).unwrap();
+			assert_eq!(cursor.seek("k".to_string()), Some(("k".to_string(), 0)));
+		}
+		tx1.set(DEFAULT, "k".to_string(), 1).unwrap();
+		tx1.commit().unwrap();
+		// tx2's own write is unrelated, but its cursor read is now stale
+		tx2.set(DEFAULT, "other".to_string(), 2).unwrap();
+		assert!(matches!(tx2.commit(), Err(Error::TxConflict)));
+	}
+
+	#[tokio::test]
+	async fn scan_rev_returns_keys_in_descending_order() {
+		let db: Db<String, i32> = Db::new(Config::default(), None, None).unwrap();
+		let mut tx = db.begin(true).await;
+		tx.set(DEFAULT, "a".to_string(), 1).unwrap();
+		tx.set(DEFAULT, "b".to_string(), 2).unwrap();
+		tx.set(DEFAULT, "c".to_string(), 3).unwrap();
+		let res = tx.scan_rev(DEFAULT, .., 10).unwrap();
+		assert_eq!(
+			res,
+			vec![("c".to_string(), 3), ("b".to_string(), 2), ("a".to_string(), 1)]
+		);
+	}
 }